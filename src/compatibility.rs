@@ -0,0 +1,221 @@
+use avro_rs::Schema;
+use serde::{Deserialize, Serialize};
+
+/// Confluent-style per-subject compatibility level. Controls which existing
+/// version(s) a newly registered schema is checked against, and in which
+/// direction. The transitive variants check the candidate against every
+/// existing version instead of just the latest one; see
+/// [`is_transitive`].
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CompatibilityLevel {
+    #[serde(rename = "BACKWARD")]
+    #[default]
+    Backward,
+    #[serde(rename = "BACKWARD_TRANSITIVE")]
+    BackwardTransitive,
+    #[serde(rename = "FORWARD")]
+    Forward,
+    #[serde(rename = "FORWARD_TRANSITIVE")]
+    ForwardTransitive,
+    #[serde(rename = "FULL")]
+    Full,
+    #[serde(rename = "FULL_TRANSITIVE")]
+    FullTransitive,
+    #[serde(rename = "NONE")]
+    None,
+}
+
+/// Does `level` require checking the candidate against every existing
+/// version, rather than just the latest one?
+pub fn is_transitive(level: CompatibilityLevel) -> bool {
+    matches!(
+        level,
+        CompatibilityLevel::BackwardTransitive
+            | CompatibilityLevel::ForwardTransitive
+            | CompatibilityLevel::FullTransitive
+    )
+}
+
+/// Is `candidate` compatible with `existing` under `level`? BACKWARD(_TRANSITIVE)
+/// means the candidate (reader) can read data written with the existing
+/// schema (writer); FORWARD(_TRANSITIVE) swaps those roles; FULL(_TRANSITIVE)
+/// requires both; NONE never rejects anything. Whether `existing` is just the
+/// latest version or one of several prior versions is decided by the caller
+/// per [`is_transitive`].
+pub fn check_compatibility(level: CompatibilityLevel, candidate: &Schema, existing: &Schema) -> bool {
+    match level {
+        CompatibilityLevel::Backward | CompatibilityLevel::BackwardTransitive => {
+            schema_resolves(candidate, existing)
+        }
+        CompatibilityLevel::Forward | CompatibilityLevel::ForwardTransitive => {
+            schema_resolves(existing, candidate)
+        }
+        CompatibilityLevel::Full | CompatibilityLevel::FullTransitive => {
+            schema_resolves(candidate, existing) && schema_resolves(existing, candidate)
+        }
+        CompatibilityLevel::None => true,
+    }
+}
+
+/// Can a reader using schema `reader` read data written with schema `writer`?
+/// Walks both schemas structurally, following Avro's own resolution rules.
+fn schema_resolves(reader: &Schema, writer: &Schema) -> bool {
+    // Every branch of a writer union must resolve against some branch of the reader.
+    if let Schema::Union(writer_union) = writer {
+        return writer_union
+            .variants()
+            .iter()
+            .all(|branch| schema_resolves(reader, branch));
+    }
+    // A writer value resolves against a reader union if any branch accepts it.
+    if let Schema::Union(reader_union) = reader {
+        return reader_union
+            .variants()
+            .iter()
+            .any(|branch| schema_resolves(branch, writer));
+    }
+
+    match (reader, writer) {
+        (
+            Schema::Record {
+                fields: reader_fields,
+                ..
+            },
+            Schema::Record {
+                fields: writer_fields,
+                ..
+            },
+        ) => reader_fields.iter().all(|reader_field| {
+            match writer_fields
+                .iter()
+                .find(|writer_field| writer_field.name == reader_field.name)
+            {
+                Some(writer_field) => schema_resolves(&reader_field.schema, &writer_field.schema),
+                // A field only the reader knows about is fine as long as it has a default.
+                None => reader_field.default.is_some(),
+            }
+        }),
+        (
+            Schema::Enum {
+                symbols: reader_symbols,
+                ..
+            },
+            Schema::Enum {
+                symbols: writer_symbols,
+                ..
+            },
+        ) => writer_symbols
+            .iter()
+            .all(|symbol| reader_symbols.contains(symbol)),
+        (
+            Schema::Fixed {
+                size: reader_size, ..
+            },
+            Schema::Fixed {
+                size: writer_size, ..
+            },
+        ) => reader_size == writer_size,
+        (Schema::Array(reader_items), Schema::Array(writer_items)) => {
+            schema_resolves(reader_items, writer_items)
+        }
+        (Schema::Map(reader_values), Schema::Map(writer_values)) => {
+            schema_resolves(reader_values, writer_values)
+        }
+        _ => promotes(writer, reader),
+    }
+}
+
+/// Avro's numeric/string promotion table: a writer value of type `from` can
+/// be read as a reader expecting type `to` when `to` appears at or after
+/// `from` in its promotion chain. `int -> long -> float -> double`, and
+/// `string` and `bytes` promote to one another.
+fn promotes(from: &Schema, to: &Schema) -> bool {
+    if schema_kind_eq(from, to) {
+        return true;
+    }
+    const NUMERIC_CHAIN: [fn(&Schema) -> bool; 4] = [is_int, is_long, is_float, is_double];
+    let from_rank = NUMERIC_CHAIN.iter().position(|is_kind| is_kind(from));
+    let to_rank = NUMERIC_CHAIN.iter().position(|is_kind| is_kind(to));
+    if let (Some(from_rank), Some(to_rank)) = (from_rank, to_rank) {
+        return to_rank >= from_rank;
+    }
+    matches!(
+        (from, to),
+        (Schema::String, Schema::Bytes) | (Schema::Bytes, Schema::String)
+    )
+}
+
+fn is_int(schema: &Schema) -> bool {
+    matches!(schema, Schema::Int)
+}
+fn is_long(schema: &Schema) -> bool {
+    matches!(schema, Schema::Long)
+}
+fn is_float(schema: &Schema) -> bool {
+    matches!(schema, Schema::Float)
+}
+fn is_double(schema: &Schema) -> bool {
+    matches!(schema, Schema::Double)
+}
+
+/// Structural equality for the schema kinds that carry no nested schema of
+/// their own (records/arrays/maps/etc. are handled by `schema_resolves`
+/// before falling back here).
+fn schema_kind_eq(a: &Schema, b: &Schema) -> bool {
+    matches!(
+        (a, b),
+        (Schema::Null, Schema::Null)
+            | (Schema::Boolean, Schema::Boolean)
+            | (Schema::Int, Schema::Int)
+            | (Schema::Long, Schema::Long)
+            | (Schema::Float, Schema::Float)
+            | (Schema::Double, Schema::Double)
+            | (Schema::Bytes, Schema::Bytes)
+            | (Schema::String, Schema::String)
+            | (Schema::Uuid, Schema::Uuid)
+            | (Schema::Date, Schema::Date)
+            | (Schema::TimeMillis, Schema::TimeMillis)
+            | (Schema::TimeMicros, Schema::TimeMicros)
+            | (Schema::TimestampMillis, Schema::TimestampMillis)
+            | (Schema::TimestampMicros, Schema::TimestampMicros)
+            | (Schema::Duration, Schema::Duration)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_transitive_flags_only_the_transitive_variants() {
+        assert!(!is_transitive(CompatibilityLevel::Backward));
+        assert!(is_transitive(CompatibilityLevel::BackwardTransitive));
+        assert!(!is_transitive(CompatibilityLevel::Forward));
+        assert!(is_transitive(CompatibilityLevel::ForwardTransitive));
+        assert!(!is_transitive(CompatibilityLevel::Full));
+        assert!(is_transitive(CompatibilityLevel::FullTransitive));
+        assert!(!is_transitive(CompatibilityLevel::None));
+    }
+
+    #[test]
+    fn backward_allows_int_promoting_to_long() {
+        let reader = Schema::parse_str("\"long\"").unwrap();
+        let writer = Schema::parse_str("\"int\"").unwrap();
+        assert!(check_compatibility(
+            CompatibilityLevel::Backward,
+            &reader,
+            &writer
+        ));
+    }
+
+    #[test]
+    fn backward_rejects_incompatible_union_branch() {
+        let reader = Schema::parse_str("[\"long\", \"string\"]").unwrap();
+        let writer = Schema::parse_str("[\"boolean\", \"long\"]").unwrap();
+        assert!(!check_compatibility(
+            CompatibilityLevel::Backward,
+            &reader,
+            &writer
+        ));
+    }
+}
@@ -1,3 +1,4 @@
+use crate::compatibility::{check_compatibility, is_transitive, CompatibilityLevel};
 use crate::VersionParam;
 use avro_rs::Schema;
 use md5::Digest;
@@ -5,13 +6,35 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::hash::Hash;
 use std::iter::Map;
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::broadcast;
+
+/// Capacity of the broadcast channel used to fan out registration events to
+/// SSE/WebSocket subscribers. Slow subscribers that fall behind by more than
+/// this many events will observe a `Lagged` error on their next `recv`.
+const EVENTS_CHANNEL_CAPACITY: usize = 256;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RegistryEvent {
+    NewVersion {
+        subject: SubjectName,
+        version: SubjectVersion,
+        id: SchemaId,
+    },
+}
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct InputSchema {
     schema: String,
 }
+impl InputSchema {
+    pub fn new(schema: String) -> Self {
+        InputSchema { schema }
+    }
+    pub fn as_str(&self) -> &str {
+        self.schema.as_str()
+    }
+}
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Subject {
@@ -19,47 +42,106 @@ pub struct Subject {
     pub name: String,
     pub version: u32,
     pub schema: String,
+    /// Soft-deleted versions are hidden from listings and name/version
+    /// lookups, but their id stays resolvable via `get_subject_by_id`.
+    #[serde(default)]
+    pub deleted: bool,
 }
 pub type SubjectVersion = u32;
 pub type SubjectName = String;
 pub type SchemaId = i32;
 pub type SchemaIdAndSubjects = (SchemaId, HashMap<SubjectName, SubjectVersion>);
-pub type SharedState = Arc<RwLock<AppState>>;
-pub fn initialize_state() -> SharedState {
-    // We suppose that the map is init using external data.
-
-    let mut app_state = AppState {
-        schemas_by_id: HashMap::new(),
-        schemas_by_name: HashMap::new(),
-        hashes: HashMap::new(),
-    };
-    app_state
-        .register_subject_version(
-            "blublu",
-            InputSchema {
-                schema: "[\"long\"]".to_string(),
-            },
-        )
-        .expect("TODO: panic message");
-    SharedState::new(RwLock::new(app_state))
-}
+
 pub struct AppState {
     schemas_by_name: HashMap<String, Vec<Subject>>,
     schemas_by_id: HashMap<i32, Subject>,
     hashes: HashMap<Digest, SchemaIdAndSubjects>,
+    config: HashMap<SubjectName, CompatibilityLevel>,
+    events: broadcast::Sender<RegistryEvent>,
+    /// The next id to hand out for a brand-new schema. Monotonically
+    /// increasing so that a permanently deleted schema's id is never
+    /// reissued to a later, unrelated registration.
+    next_schema_id: SchemaId,
+}
+
+impl Default for AppState {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl AppState {
+    /// An empty registry with no subjects, config, or history. Stores build
+    /// on this and decide for themselves how (or whether) to seed or
+    /// restore prior state.
+    pub fn new() -> Self {
+        let (events, _) = broadcast::channel(EVENTS_CHANNEL_CAPACITY);
+        AppState {
+            schemas_by_id: HashMap::new(),
+            schemas_by_name: HashMap::new(),
+            hashes: HashMap::new(),
+            config: HashMap::new(),
+            events,
+            next_schema_id: 1,
+        }
+    }
+    /// Subscribe to the stream of registration events (new schema ids, new
+    /// subject versions). Each call returns an independent receiver, so the
+    /// SSE and WebSocket endpoints can both listen without stealing events
+    /// from one another.
+    pub fn subscribe_events(&self) -> broadcast::Receiver<RegistryEvent> {
+        self.events.subscribe()
+    }
+
+    /// The compatibility level in effect for `subject`, falling back to the
+    /// registry-wide default (`BACKWARD`) if none was set explicitly.
+    pub fn get_compatibility(&self, subject: &str) -> CompatibilityLevel {
+        self.config.get(subject).copied().unwrap_or_default()
+    }
+    pub fn set_compatibility(&mut self, subject: &str, level: CompatibilityLevel) {
+        self.config.insert(subject.to_string(), level);
+    }
+    /// Is `candidate` compatible with the existing registered version(s) of
+    /// `subject`, under that subject's configured compatibility level? Plain
+    /// BACKWARD/FORWARD/FULL only look at the latest version; their
+    /// `_TRANSITIVE` counterparts check every non-deleted version. A subject
+    /// with no prior versions always accepts.
+    pub fn is_compatible(&self, subject: &str, candidate: &Schema) -> Result<bool, String> {
+        let level = self.get_compatibility(subject);
+        let Some(versions) = self.schemas_by_name.get(subject) else {
+            return Ok(true);
+        };
+
+        let mut to_check: Vec<&Subject> = versions.iter().filter(|s| !s.deleted).collect();
+        if !is_transitive(level) {
+            to_check = to_check
+                .into_iter()
+                .max_by_key(|s| s.version)
+                .into_iter()
+                .collect();
+        }
+
+        for existing_version in to_check {
+            let existing = Schema::parse_str(existing_version.schema.as_str())
+                .map_err(|e| format!("Stored schema could not be parsed: {e}"))?;
+            if !check_compatibility(level, candidate, &existing) {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
     pub fn get_subject_by_name_and_version(
         &self,
         name: &str,
         version_param: VersionParam,
     ) -> Option<Subject> {
+        metrics::counter!("registry_lookups_total").increment(1);
         match version_param {
             VersionParam::Latest => Some(
                 self.schemas_by_name
                     .get(name)?
                     .iter()
+                    .filter(|x| !x.deleted)
                     .max_by_key(|x| x.version)?
                     .clone(),
             ),
@@ -68,7 +150,7 @@ impl AppState {
                     .schemas_by_name
                     .get(name)?
                     .iter()
-                    .filter(|x1| x1.version == version)
+                    .filter(|x1| x1.version == version && !x1.deleted)
                     .collect();
                 if subject.is_empty() {
                     None
@@ -83,14 +165,20 @@ impl AppState {
             .schemas_by_name
             .get(subject)?
             .iter()
+            .filter(|s| !s.deleted)
             .map(|s| s.version)
             .collect();
         Some(versions)
     }
     pub fn list_subjects(&self) -> Vec<String> {
-        self.schemas_by_name.keys().into_iter().cloned().collect()
+        self.schemas_by_name
+            .iter()
+            .filter(|(_, versions)| versions.iter().any(|s| !s.deleted))
+            .map(|(name, _)| name.clone())
+            .collect()
     }
     pub fn get_subject_by_id(&self, id: i32) -> Option<Subject> {
+        metrics::counter!("registry_lookups_total").increment(1);
         Some(self.schemas_by_id.get(&id)?.clone())
     }
     pub fn register_subject_version(
@@ -101,25 +189,57 @@ impl AppState {
         let parsed_schema = Schema::parse_str(body.schema.as_str())
             .map_err(|e| format!("Schema could could be parsed: {e}"))?;
 
+        if !self.is_compatible(subject_name, &parsed_schema)? {
+            return Err(format!(
+                "Schema is not {:?} compatible with the existing version(s) of \"{subject_name}\"",
+                self.get_compatibility(subject_name)
+            ));
+        }
+
         let md5 = md5::compute(parsed_schema.canonical_form());
         match self.hashes.get(&md5) {
             // Found Subjects, need to confirm same or new
             Some(found) => {
-                match found.1.get(subject_name) {
-                    Some(s) => Ok(found.0),
+                metrics::counter!("registry_dedup_cache_hits_total").increment(1);
+                let schema_id = found.0;
+                match found.1.get(subject_name).copied() {
+                    // Already has this exact schema under this subject. If that
+                    // version was soft-deleted, undelete it rather than
+                    // reporting success while leaving it invisible.
+                    Some(existing_version) => {
+                        self.undelete_if_present(subject_name, existing_version);
+                        Ok(schema_id)
+                    }
                     None => {
-                        let schema_id = found.0;
                         self.register_subject_with_id(schema_id, subject_name.to_string(), body.schema, md5)
                     }
                 }
             },
             // Create the schema
             None => {
-                let next_schema_id = self.schemas_by_id.keys().max().map_or(1,|t| t + 1);
+                let next_schema_id = self.next_schema_id;
+                self.next_schema_id += 1;
                 self.register_subject_with_id(next_schema_id, subject_name.to_string(), body.schema, md5)
             }
         }
     }
+    /// Clears the `deleted` flag on `subject_name`'s `version` if it is
+    /// currently soft-deleted, keeping `schemas_by_id` in sync. No-op if the
+    /// version isn't soft-deleted (or doesn't exist).
+    fn undelete_if_present(&mut self, subject_name: &str, version: SubjectVersion) {
+        let restored = self.schemas_by_name.get_mut(subject_name).and_then(|versions| {
+            let subject = versions.iter_mut().find(|s| s.version == version)?;
+            if subject.deleted {
+                subject.deleted = false;
+                Some(subject.clone())
+            } else {
+                None
+            }
+        });
+        if let Some(subject) = restored {
+            self.schemas_by_id.insert(subject.id, subject);
+        }
+    }
     fn register_subject_with_id(&mut self, schema_id: SchemaId, subject_name: SubjectName, schema: String, md5: Digest)->Result<i32, String>{
         let mut all_subject_versions = match self.schemas_by_name.get(&subject_name) {
             Some(x) => x.clone(),
@@ -131,15 +251,13 @@ impl AppState {
             .max()
             .unwrap_or(0)
             + 1;
-        if new_version > 3 {
-            return Err("Sorry, 3 versions maximum".to_string());
-        }
 
         let subject = Subject {
             id: schema_id,
             name: subject_name.clone(),
             version: new_version,
             schema,
+            deleted: false,
         };
         all_subject_versions.push(subject.clone());
 
@@ -154,6 +272,211 @@ impl AppState {
         self.schemas_by_name
             .insert(String::from(&subject_name), all_subject_versions.clone());
         self.schemas_by_id.insert(schema_id, subject);
+
+        metrics::counter!("registry_schemas_registered_total").increment(1);
+
+        // Best-effort: it's fine if nobody is listening.
+        let _ = self.events.send(RegistryEvent::NewVersion {
+            subject: subject_name,
+            version: new_version,
+            id: schema_id,
+        });
+
         Ok(schema_id)
     }
+
+    /// Deletes a single version of `subject_name`. Soft delete (the
+    /// default) hides it from listings and lookups but leaves its id
+    /// resolvable; `permanent` removes it and its dedup entry outright.
+    /// Returns the version numbers that were deleted.
+    pub fn delete_subject_version(
+        &mut self,
+        subject_name: &str,
+        version: SubjectVersion,
+        permanent: bool,
+    ) -> Result<Vec<SubjectVersion>, String> {
+        let versions = self
+            .schemas_by_name
+            .get_mut(subject_name)
+            .ok_or_else(|| "Subject not found".to_string())?;
+        let index = versions
+            .iter()
+            .position(|s| s.version == version && (permanent || !s.deleted))
+            .ok_or_else(|| "Version not found".to_string())?;
+
+        if permanent {
+            let removed = versions.remove(index);
+            if versions.is_empty() {
+                self.schemas_by_name.remove(subject_name);
+            }
+            self.cleanup_dangling_references(&removed);
+        } else {
+            versions[index].deleted = true;
+        }
+
+        Ok(vec![version])
+    }
+
+    /// Deletes every version of `subject_name`. Returns the version
+    /// numbers that were deleted.
+    pub fn delete_subject(
+        &mut self,
+        subject_name: &str,
+        permanent: bool,
+    ) -> Result<Vec<SubjectVersion>, String> {
+        if permanent {
+            let versions = self
+                .schemas_by_name
+                .remove(subject_name)
+                .ok_or_else(|| "Subject not found".to_string())?;
+            let deleted_versions = versions.iter().map(|s| s.version).collect();
+            for subject in &versions {
+                self.cleanup_dangling_references(subject);
+            }
+            Ok(deleted_versions)
+        } else {
+            let versions = self
+                .schemas_by_name
+                .get_mut(subject_name)
+                .ok_or_else(|| "Subject not found".to_string())?;
+            let mut deleted_versions = Vec::new();
+            for subject in versions.iter_mut().filter(|s| !s.deleted) {
+                subject.deleted = true;
+                deleted_versions.push(subject.version);
+            }
+            Ok(deleted_versions)
+        }
+    }
+
+    /// After a permanent delete, drop the now-stale dedup entry for
+    /// `removed`'s schema id, and drop the id itself from `schemas_by_id`
+    /// if no subject references it any more (the same schema id can be
+    /// shared across subjects via the MD5 dedup map).
+    fn cleanup_dangling_references(&mut self, removed: &Subject) {
+        let stale_hashes: Vec<Digest> = self
+            .hashes
+            .iter()
+            .filter(|(_, (id, _))| *id == removed.id)
+            .map(|(digest, _)| *digest)
+            .collect();
+        for digest in stale_hashes {
+            if let Some((_, subjects)) = self.hashes.get_mut(&digest) {
+                subjects.remove(&removed.name);
+                if subjects.is_empty() {
+                    self.hashes.remove(&digest);
+                }
+            }
+        }
+
+        let still_referenced = self
+            .schemas_by_name
+            .values()
+            .flatten()
+            .any(|s| s.id == removed.id);
+        if !still_referenced {
+            self.schemas_by_id.remove(&removed.id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reregistering_a_soft_deleted_version_undeletes_it() {
+        let mut state = AppState::new();
+        let id = state
+            .register_subject_version("s1", InputSchema::new("\"long\"".to_string()))
+            .unwrap();
+        state.delete_subject_version("s1", 1, false).unwrap();
+        assert!(state
+            .get_subject_by_name_and_version("s1", VersionParam::Version(1))
+            .is_none());
+
+        let rereg_id = state
+            .register_subject_version("s1", InputSchema::new("\"long\"".to_string()))
+            .unwrap();
+
+        assert_eq!(id, rereg_id);
+        let restored = state
+            .get_subject_by_name_and_version("s1", VersionParam::Version(1))
+            .expect("version should be visible again after re-registration");
+        assert!(!restored.deleted);
+        assert_eq!(state.get_subject_versions("s1").unwrap(), vec![1]);
+    }
+
+    #[test]
+    fn permanent_delete_removes_dangling_dedup_entries() {
+        let mut state = AppState::new();
+        let id = state
+            .register_subject_version("s1", InputSchema::new("\"long\"".to_string()))
+            .unwrap();
+        state.delete_subject_version("s1", 1, true).unwrap();
+
+        assert!(state.get_subject_by_id(id).is_none());
+        assert!(state
+            .get_subject_by_name_and_version("s1", VersionParam::Version(1))
+            .is_none());
+        assert!(state.hashes.is_empty());
+    }
+
+    #[test]
+    fn permanently_deleted_schema_ids_are_never_reused() {
+        let mut state = AppState::new();
+        let id1 = state
+            .register_subject_version("s1", InputSchema::new("\"long\"".to_string()))
+            .unwrap();
+        state.delete_subject_version("s1", 1, true).unwrap();
+
+        let id2 = state
+            .register_subject_version("s2", InputSchema::new("\"string\"".to_string()))
+            .unwrap();
+
+        assert_ne!(id1, id2, "a freed schema id must never be reissued");
+        assert!(state.get_subject_by_id(id1).is_none());
+        assert_eq!(
+            state.get_subject_by_id(id2).unwrap().name,
+            "s2",
+            "id1's slot must not resolve to s2's schema"
+        );
+    }
+
+    #[test]
+    fn transitive_backward_checks_every_version_not_just_latest() {
+        let mut state = AppState::new();
+        state.set_compatibility("s1", CompatibilityLevel::None);
+        state
+            .register_subject_version("s1", InputSchema::new("[\"boolean\", \"long\"]".to_string()))
+            .unwrap();
+        state
+            .register_subject_version("s1", InputSchema::new("[\"long\"]".to_string()))
+            .unwrap();
+
+        let candidate = Schema::parse_str("[\"long\", \"string\"]").unwrap();
+
+        state.set_compatibility("s1", CompatibilityLevel::Backward);
+        assert!(
+            state.is_compatible("s1", &candidate).unwrap(),
+            "plain BACKWARD only checks the latest version"
+        );
+
+        state.set_compatibility("s1", CompatibilityLevel::BackwardTransitive);
+        assert!(
+            !state.is_compatible("s1", &candidate).unwrap(),
+            "BACKWARD_TRANSITIVE must also reject against the older, incompatible version"
+        );
+    }
+
+    #[test]
+    fn soft_deleted_subject_id_stays_resolvable() {
+        let mut state = AppState::new();
+        let id = state
+            .register_subject_version("s1", InputSchema::new("\"long\"".to_string()))
+            .unwrap();
+        state.delete_subject("s1", false).unwrap();
+
+        assert!(state.get_subject_by_id(id).is_some());
+        assert!(state.list_subjects().is_empty());
+    }
 }
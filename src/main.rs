@@ -1,23 +1,36 @@
 use axum::{
     error_handling::HandleErrorLayer,
-    extract::{Json, Path, State},
+    extract::{Extension, Json, Path, Query, State},
     http::StatusCode,
-    response::IntoResponse,
-    routing::get,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse,
+    },
+    routing::{delete, get, post},
     Router,
 };
 use axum_macros::debug_handler;
+use axum_prometheus::{PrometheusHandle, PrometheusMetricLayerBuilder};
 use eyre::Result;
+use futures::stream::Stream;
 use serde::{de, Deserialize, Deserializer};
 use serde_json::{json, Value};
 use std::{net::SocketAddr, sync::Arc, time::Duration};
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::StreamExt as _;
 
-use crate::domain::{InputSchema, SharedState};
+use crate::compatibility::CompatibilityLevel;
+use crate::domain::InputSchema;
+use crate::store::SharedState;
 use tower::{BoxError, ServiceBuilder};
 use tower_http::trace::TraceLayer;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
+pub mod compatibility;
 pub mod domain;
+pub mod store;
+pub mod ws;
 
 pub enum VersionParam {
     Version(u32),
@@ -44,9 +57,8 @@ async fn list_subject_versions(
     State(data): State<SharedState>,
 ) -> Result<impl IntoResponse, StatusCode> {
     let versions: Vec<u32> = data
-        .read()
-        .await
         .get_subject_versions(&subject)
+        .await
         .ok_or(StatusCode::NOT_FOUND)?;
 
     Ok(Json(versions))
@@ -57,9 +69,8 @@ async fn get_schema_by_subject_and_version(
     State(data): State<SharedState>,
 ) -> Result<impl IntoResponse, StatusCode> {
     Ok(Json(
-        data.read()
+        data.get_subject_by_name_and_version(&subject, version)
             .await
-            .get_subject_by_name_and_version(&subject, version)
             .ok_or(StatusCode::NOT_FOUND)?,
     ))
 }
@@ -68,9 +79,8 @@ async fn get_schema_string_by_subject_and_version(
     State(data): State<SharedState>,
 ) -> Result<impl IntoResponse, StatusCode> {
     Ok(Json(
-        data.read()
+        data.get_subject_by_name_and_version(&subject, version)
             .await
-            .get_subject_by_name_and_version(&subject, version)
             .map(|subject| {
                 let value: serde_json::Result<Value> =
                     serde_json::from_str(subject.schema.as_str());
@@ -85,15 +95,14 @@ async fn get_subject_by_id(
     State(data): State<SharedState>,
 ) -> Result<impl IntoResponse, StatusCode> {
     let result = data
-        .read()
-        .await
         .get_subject_by_id(id)
+        .await
         .ok_or(StatusCode::NOT_FOUND)?;
     Ok(Json(result))
 }
 
 async fn list_subjects(State(data): State<SharedState>) -> impl IntoResponse {
-    let subjects: Vec<String> = data.read().await.list_subjects();
+    let subjects: Vec<String> = data.list_subjects().await;
     (StatusCode::OK, Json(subjects))
 }
 #[debug_handler]
@@ -102,9 +111,7 @@ async fn register_subject_version(
     State(data): State<SharedState>,
     Json(body): Json<InputSchema>,
 ) -> impl IntoResponse {
-    let mut state = data.write().await;
-
-    match state.register_subject_version(name.as_str(), body) {
+    match data.register_subject_version(name.as_str(), body).await {
         Ok(next_id) => (
             StatusCode::CREATED,
             Json(json!({
@@ -118,8 +125,149 @@ async fn register_subject_version(
     }
 }
 
+#[debug_handler]
+async fn check_schema_compatibility(
+    Path((subject, version)): Path<(String, VersionParam)>,
+    State(data): State<SharedState>,
+    Json(body): Json<InputSchema>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let against = data
+        .get_subject_by_name_and_version(&subject, version)
+        .await
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let candidate = avro_rs::Schema::parse_str(body.as_str())
+        .map_err(|_| StatusCode::UNPROCESSABLE_ENTITY)?;
+    let existing = avro_rs::Schema::parse_str(against.schema.as_str())
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let level = data.get_compatibility(&subject).await;
+    let is_compatible = compatibility::check_compatibility(level, &candidate, &existing);
+
+    Ok(Json(json!({ "is_compatible": is_compatible })))
+}
+
+async fn get_config(
+    Path(subject): Path<String>,
+    State(data): State<SharedState>,
+) -> impl IntoResponse {
+    let level = data.get_compatibility(&subject).await;
+    Json(json!({ "compatibilityLevel": level }))
+}
+
+#[derive(Deserialize)]
+struct SetCompatibility {
+    compatibility: CompatibilityLevel,
+}
+
+async fn put_config(
+    Path(subject): Path<String>,
+    State(data): State<SharedState>,
+    Json(body): Json<SetCompatibility>,
+) -> impl IntoResponse {
+    data.set_compatibility(&subject, body.compatibility).await;
+    Json(json!({ "compatibilityLevel": body.compatibility }))
+}
+
+/// Registers or looks up many subjects/versions in one round trip, under a
+/// single write lock on the store — the whole batch is atomic with respect
+/// to any other request, not just each item within it. Each item still
+/// succeeds or fails independently, mirrored back at the same index in the
+/// response array; one failing item does not abort the rest.
+async fn batch(
+    State(data): State<SharedState>,
+    Json(ops): Json<Vec<store::BatchOp>>,
+) -> impl IntoResponse {
+    let results = data.batch(ops).await;
+    (StatusCode::OK, Json(results))
+}
+
+#[derive(Deserialize)]
+struct DeleteParams {
+    #[serde(default)]
+    permanent: bool,
+}
+
+/// Deletes every version of `subject`. Soft delete (the default) hides the
+/// subject from listings and lookups while leaving its schema ids
+/// resolvable; `?permanent=true` removes it, and its dedup entries, for
+/// good. Returns the version numbers that were deleted.
+async fn delete_subject(
+    Path(subject): Path<String>,
+    Query(params): Query<DeleteParams>,
+    State(data): State<SharedState>,
+) -> Result<impl IntoResponse, StatusCode> {
+    data.delete_subject(&subject, params.permanent)
+        .await
+        .map(Json)
+        .map_err(|_| StatusCode::NOT_FOUND)
+}
+
+/// Deletes a single version of `subject`, with the same soft/permanent
+/// semantics as `delete_subject`.
+async fn delete_subject_version(
+    Path((subject, version)): Path<(String, u32)>,
+    Query(params): Query<DeleteParams>,
+    State(data): State<SharedState>,
+) -> Result<impl IntoResponse, StatusCode> {
+    data.delete_subject_version(&subject, version, params.permanent)
+        .await
+        .map(Json)
+        .map_err(|_| StatusCode::NOT_FOUND)
+}
+
+/// Reports whether the store is reachable and how many subjects it holds,
+/// so a load balancer or orchestrator can use this as a liveness probe.
+async fn health(State(data): State<SharedState>) -> impl IntoResponse {
+    let subjects = data.list_subjects().await;
+    Json(json!({
+        "status": "ok",
+        "store_reachable": true,
+        "subjects": subjects.len(),
+    }))
+}
+
+/// Renders Prometheus-format metrics, refreshing the registry-size gauges
+/// just before handing off to the exporter's own handle.
+async fn metrics_handler(
+    State(data): State<SharedState>,
+    Extension(metric_handle): Extension<PrometheusHandle>,
+) -> String {
+    let subjects = data.list_subjects().await;
+    let mut version_count = 0usize;
+    for subject in &subjects {
+        version_count += data
+            .get_subject_versions(subject)
+            .await
+            .map(|versions| versions.len())
+            .unwrap_or(0);
+    }
+    metrics::gauge!("registry_subjects_total").set(subjects.len() as f64);
+    metrics::gauge!("registry_versions_total").set(version_count as f64);
+    metric_handle.render()
+}
+
+/// Streams a JSON event every time a new schema id or subject version is
+/// registered, so tooling can react live instead of polling
+/// `list_subject_versions`.
+async fn subject_events(
+    State(data): State<SharedState>,
+) -> Sse<impl Stream<Item = Result<Event, BroadcastStreamRecvError>>> {
+    let receiver = data.subscribe_events().await;
+    let stream = BroadcastStream::new(receiver).map(|event| {
+        let event = event?;
+        Ok(Event::default().json_data(event).unwrap_or_else(|_| {
+            Event::default().comment("failed to serialize registry event")
+        }))
+    });
+
+    Sse::new(stream).keep_alive(
+        KeepAlive::new()
+            .interval(Duration::from_secs(15))
+            .text("keep-alive"),
+    )
+}
+
 #[tokio::main]
-async fn main() {
+async fn main() -> Result<()> {
     tracing_subscriber::registry()
         .with(tracing_subscriber::EnvFilter::new(
             std::env::var("RUST_LOG")
@@ -128,7 +276,11 @@ async fn main() {
         .with(tracing_subscriber::fmt::layer())
         .init();
 
-    let shared = domain::initialize_state();
+    let shared = store::build_store().await?;
+    let (prometheus_layer, metric_handle) = PrometheusMetricLayerBuilder::new()
+        .with_default_metrics()
+        .build_pair();
+
     let app = Router::with_state(Arc::clone(&shared))
         .route(
             "/subjects/:subject/versions",
@@ -136,7 +288,7 @@ async fn main() {
         )
         .route(
             "/subjects/:subject/versions/:version",
-            get(get_schema_by_subject_and_version),
+            get(get_schema_by_subject_and_version).delete(delete_subject_version),
         )
         .route(
             "/subjects/:subject/versions/:version/schema",
@@ -144,6 +296,17 @@ async fn main() {
         )
         .route("/schemas/:id", get(get_subject_by_id))
         .route("/subjects", get(list_subjects))
+        .route("/subjects/:subject", delete(delete_subject))
+        .route("/subjects/events", get(subject_events))
+        .route("/ws", get(ws::ws_handler))
+        .route(
+            "/compatibility/subjects/:subject/versions/:version",
+            post(check_schema_compatibility),
+        )
+        .route("/config/:subject", get(get_config).put(put_config))
+        .route("/batch", post(batch))
+        .route("/health", get(health))
+        .route("/metrics", get(metrics_handler))
         // Add middleware to all routes
         .layer(
             ServiceBuilder::new()
@@ -153,6 +316,8 @@ async fn main() {
                 .concurrency_limit(1024)
                 .timeout(Duration::from_secs(10))
                 .layer(TraceLayer::new_for_http())
+                .layer(prometheus_layer)
+                .layer(Extension(metric_handle))
                 .into_inner(),
         );
 
@@ -161,8 +326,8 @@ async fn main() {
     tracing::debug!("listening on {}", addr);
     axum::Server::bind(&addr)
         .serve(app.into_make_service())
-        .await
-        .unwrap();
+        .await?;
+    Ok(())
 }
 
 async fn handle_error(error: BoxError) -> impl IntoResponse {
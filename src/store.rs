@@ -0,0 +1,507 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{broadcast, Mutex, RwLock, RwLockWriteGuard};
+
+use crate::compatibility::CompatibilityLevel;
+use crate::domain::{AppState, InputSchema, RegistryEvent, SchemaId, Subject, SubjectVersion};
+use crate::VersionParam;
+
+/// One operation in a `/batch` request body.
+#[derive(Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum BatchOp {
+    Register { subject: String, schema: String },
+    Lookup { subject: String, version: VersionParam },
+}
+
+/// Applies one batch op against an already-locked `AppState`, returning the
+/// JSON result to report back to the caller and, for a successful
+/// registration, the write-ahead-log entry a persistent store should append.
+fn apply_batch_op(state: &mut AppState, op: BatchOp) -> (Value, Option<WalEntry>) {
+    match op {
+        BatchOp::Register { subject, schema } => {
+            match state.register_subject_version(&subject, InputSchema::new(schema.clone())) {
+                Ok(id) => (
+                    json!({ "success": true, "result": { "id": id } }),
+                    Some(WalEntry::Register { subject, schema }),
+                ),
+                Err(e) => (json!({ "success": false, "error": e }), None),
+            }
+        }
+        BatchOp::Lookup { subject, version } => {
+            let result = match state.get_subject_by_name_and_version(&subject, version) {
+                Some(subject) => json!({ "success": true, "result": subject }),
+                None => json!({ "success": false, "error": "not found" }),
+            };
+            (result, None)
+        }
+    }
+}
+
+/// The schema registered for every fresh store, matching the registry's
+/// historical "blublu" demo subject.
+const SEED_SUBJECT: &str = "blublu";
+const SEED_SCHEMA: &str = "[\"long\"]";
+
+pub type SharedState = Arc<dyn SchemaStore>;
+
+/// Persistence backend for the registry, abstracted away from the Axum
+/// handlers so `AppState`'s in-memory maps can be swapped for something
+/// that survives a restart without touching a single route.
+#[async_trait]
+pub trait SchemaStore: Send + Sync {
+    async fn get_subject_by_id(&self, id: SchemaId) -> Option<Subject>;
+    async fn get_subject_by_name_and_version(
+        &self,
+        name: &str,
+        version: VersionParam,
+    ) -> Option<Subject>;
+    async fn get_subject_versions(&self, subject: &str) -> Option<Vec<SubjectVersion>>;
+    async fn list_subjects(&self) -> Vec<String>;
+    async fn register_subject_version(
+        &self,
+        subject_name: &str,
+        body: InputSchema,
+    ) -> Result<SchemaId, String>;
+    async fn subscribe_events(&self) -> broadcast::Receiver<RegistryEvent>;
+    async fn get_compatibility(&self, subject: &str) -> CompatibilityLevel;
+    async fn set_compatibility(&self, subject: &str, level: CompatibilityLevel);
+    async fn delete_subject_version(
+        &self,
+        subject_name: &str,
+        version: SubjectVersion,
+        permanent: bool,
+    ) -> Result<Vec<SubjectVersion>, String>;
+    async fn delete_subject(
+        &self,
+        subject_name: &str,
+        permanent: bool,
+    ) -> Result<Vec<SubjectVersion>, String>;
+    /// Runs every op in `ops` against a single, consistent snapshot of the
+    /// store — the whole batch is atomic with respect to any other store
+    /// operation, not just each item individually.
+    async fn batch(&self, ops: Vec<BatchOp>) -> Vec<Value>;
+}
+
+/// The original behavior: schemas, versions and ids live only in process
+/// memory and are lost on restart.
+pub struct InMemoryStore {
+    state: RwLock<AppState>,
+}
+
+impl InMemoryStore {
+    /// An empty store with no seeded subjects.
+    pub fn empty() -> Self {
+        InMemoryStore {
+            state: RwLock::new(AppState::new()),
+        }
+    }
+    /// A store pre-populated with the registry's demo subject, matching
+    /// what `initialize_state` used to do before stores existed.
+    pub fn seeded() -> Self {
+        let mut state = AppState::new();
+        state
+            .register_subject_version(SEED_SUBJECT, InputSchema::new(SEED_SCHEMA.to_string()))
+            .expect("seeding the demo subject should never fail");
+        InMemoryStore {
+            state: RwLock::new(state),
+        }
+    }
+    /// Runs `f` against the store's state under a single write lock, so
+    /// batch operations observe one consistent snapshot and can't interleave
+    /// with any other registration, delete, or lookup.
+    pub(crate) async fn with_write_lock<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut AppState) -> R,
+    {
+        let mut state = self.state.write().await;
+        f(&mut state)
+    }
+    /// Acquires the write lock directly, for callers that need to hold it
+    /// across an `.await` after mutating — e.g. appending a write-ahead-log
+    /// entry before releasing it, so the mutation and its log entry stay
+    /// atomic with respect to every other writer.
+    pub(crate) async fn write(&self) -> RwLockWriteGuard<'_, AppState> {
+        self.state.write().await
+    }
+}
+
+#[async_trait]
+impl SchemaStore for InMemoryStore {
+    async fn get_subject_by_id(&self, id: SchemaId) -> Option<Subject> {
+        self.state.read().await.get_subject_by_id(id)
+    }
+    async fn get_subject_by_name_and_version(
+        &self,
+        name: &str,
+        version: VersionParam,
+    ) -> Option<Subject> {
+        self.state
+            .read()
+            .await
+            .get_subject_by_name_and_version(name, version)
+    }
+    async fn get_subject_versions(&self, subject: &str) -> Option<Vec<SubjectVersion>> {
+        self.state.read().await.get_subject_versions(subject)
+    }
+    async fn list_subjects(&self) -> Vec<String> {
+        self.state.read().await.list_subjects()
+    }
+    async fn register_subject_version(
+        &self,
+        subject_name: &str,
+        body: InputSchema,
+    ) -> Result<SchemaId, String> {
+        self.state
+            .write()
+            .await
+            .register_subject_version(subject_name, body)
+    }
+    async fn subscribe_events(&self) -> broadcast::Receiver<RegistryEvent> {
+        self.state.read().await.subscribe_events()
+    }
+    async fn get_compatibility(&self, subject: &str) -> CompatibilityLevel {
+        self.state.read().await.get_compatibility(subject)
+    }
+    async fn set_compatibility(&self, subject: &str, level: CompatibilityLevel) {
+        self.state.write().await.set_compatibility(subject, level)
+    }
+    async fn delete_subject_version(
+        &self,
+        subject_name: &str,
+        version: SubjectVersion,
+        permanent: bool,
+    ) -> Result<Vec<SubjectVersion>, String> {
+        self.state
+            .write()
+            .await
+            .delete_subject_version(subject_name, version, permanent)
+    }
+    async fn delete_subject(
+        &self,
+        subject_name: &str,
+        permanent: bool,
+    ) -> Result<Vec<SubjectVersion>, String> {
+        self.state.write().await.delete_subject(subject_name, permanent)
+    }
+    async fn batch(&self, ops: Vec<BatchOp>) -> Vec<Value> {
+        self.with_write_lock(|state| {
+            ops.into_iter()
+                .map(|op| apply_batch_op(state, op).0)
+                .collect()
+        })
+        .await
+    }
+}
+
+/// One entry in the write-ahead log: a successful registration, a deletion,
+/// or a compatibility config change, replayed in order to reconstruct a
+/// `FileBackedStore`'s state on boot.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum WalEntry {
+    Register {
+        subject: String,
+        schema: String,
+    },
+    Delete {
+        subject: String,
+        version: Option<SubjectVersion>,
+        permanent: bool,
+    },
+    Config {
+        subject: String,
+        level: CompatibilityLevel,
+    },
+}
+
+/// A store that replays a write-ahead log of registrations, deletes, and
+/// compatibility config changes on boot, then appends every new one of
+/// those to that same log. Because schema ids are handed out sequentially in
+/// registration order, replaying the log in order reconstructs
+/// exactly the same ids, versions, and config the registry had before the
+/// restart.
+pub struct FileBackedStore {
+    inner: InMemoryStore,
+    log: Mutex<tokio::fs::File>,
+}
+
+impl FileBackedStore {
+    pub async fn open(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let path: PathBuf = path.as_ref().to_path_buf();
+        let inner = InMemoryStore::empty();
+        let log_existed = path.exists();
+
+        if log_existed {
+            let contents = tokio::fs::read_to_string(&path).await?;
+            for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+                let entry: WalEntry = serde_json::from_str(line)
+                    .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+                let result = match entry {
+                    WalEntry::Register { subject, schema } => inner
+                        .register_subject_version(&subject, InputSchema::new(schema))
+                        .await
+                        .map(|_| ()),
+                    WalEntry::Delete {
+                        subject,
+                        version: Some(version),
+                        permanent,
+                    } => inner
+                        .delete_subject_version(&subject, version, permanent)
+                        .await
+                        .map(|_| ()),
+                    WalEntry::Delete {
+                        subject,
+                        version: None,
+                        permanent,
+                    } => inner.delete_subject(&subject, permanent).await.map(|_| ()),
+                    WalEntry::Config { subject, level } => {
+                        inner.set_compatibility(&subject, level).await;
+                        Ok(())
+                    }
+                };
+                result.map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+            }
+        }
+
+        let file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .await?;
+
+        let store = FileBackedStore {
+            inner,
+            log: Mutex::new(file),
+        };
+
+        if !log_existed {
+            // Fresh store: seed the same demo subject InMemoryStore ships with,
+            // and record it so the seed survives the next restart too.
+            store
+                .register_subject_version(SEED_SUBJECT, InputSchema::new(SEED_SCHEMA.to_string()))
+                .await
+                .expect("seeding the demo subject should never fail");
+        }
+
+        Ok(store)
+    }
+
+    async fn append(&self, entry: &WalEntry) -> Result<(), String> {
+        let mut line = serde_json::to_string(entry).map_err(|e| e.to_string())?;
+        line.push('\n');
+
+        let mut log = self.log.lock().await;
+        log.write_all(line.as_bytes())
+            .await
+            .map_err(|e| e.to_string())?;
+        log.flush().await.map_err(|e| e.to_string())
+    }
+}
+
+#[async_trait]
+impl SchemaStore for FileBackedStore {
+    async fn get_subject_by_id(&self, id: SchemaId) -> Option<Subject> {
+        self.inner.get_subject_by_id(id).await
+    }
+    async fn get_subject_by_name_and_version(
+        &self,
+        name: &str,
+        version: VersionParam,
+    ) -> Option<Subject> {
+        self.inner.get_subject_by_name_and_version(name, version).await
+    }
+    async fn get_subject_versions(&self, subject: &str) -> Option<Vec<SubjectVersion>> {
+        self.inner.get_subject_versions(subject).await
+    }
+    async fn list_subjects(&self) -> Vec<String> {
+        self.inner.list_subjects().await
+    }
+    async fn register_subject_version(
+        &self,
+        subject_name: &str,
+        body: InputSchema,
+    ) -> Result<SchemaId, String> {
+        let schema = body.as_str().to_string();
+        // Hold the write lock across the append below: otherwise a second
+        // concurrent writer could mutate and append its own entry in between
+        // our mutation and our append, reordering the log relative to what
+        // actually happened in memory.
+        let mut state = self.inner.write().await;
+        let id = state.register_subject_version(subject_name, body)?;
+        self.append(&WalEntry::Register {
+            subject: subject_name.to_string(),
+            schema,
+        })
+        .await?;
+        Ok(id)
+    }
+    async fn subscribe_events(&self) -> broadcast::Receiver<RegistryEvent> {
+        self.inner.subscribe_events().await
+    }
+    async fn get_compatibility(&self, subject: &str) -> CompatibilityLevel {
+        self.inner.get_compatibility(subject).await
+    }
+    async fn set_compatibility(&self, subject: &str, level: CompatibilityLevel) {
+        let mut state = self.inner.write().await;
+        state.set_compatibility(subject, level);
+        if let Err(e) = self
+            .append(&WalEntry::Config {
+                subject: subject.to_string(),
+                level,
+            })
+            .await
+        {
+            tracing::error!("failed to append compatibility change to write-ahead log: {e}");
+        }
+    }
+    async fn delete_subject_version(
+        &self,
+        subject_name: &str,
+        version: SubjectVersion,
+        permanent: bool,
+    ) -> Result<Vec<SubjectVersion>, String> {
+        let mut state = self.inner.write().await;
+        let deleted = state.delete_subject_version(subject_name, version, permanent)?;
+        self.append(&WalEntry::Delete {
+            subject: subject_name.to_string(),
+            version: Some(version),
+            permanent,
+        })
+        .await?;
+        Ok(deleted)
+    }
+    async fn delete_subject(
+        &self,
+        subject_name: &str,
+        permanent: bool,
+    ) -> Result<Vec<SubjectVersion>, String> {
+        let mut state = self.inner.write().await;
+        let deleted = state.delete_subject(subject_name, permanent)?;
+        self.append(&WalEntry::Delete {
+            subject: subject_name.to_string(),
+            version: None,
+            permanent,
+        })
+        .await?;
+        Ok(deleted)
+    }
+    async fn batch(&self, ops: Vec<BatchOp>) -> Vec<Value> {
+        let mut state = self.inner.write().await;
+        let mut results = Vec::with_capacity(ops.len());
+        for op in ops {
+            let (result, entry) = apply_batch_op(&mut state, op);
+            results.push(result);
+            if let Some(entry) = entry {
+                if let Err(e) = self.append(&entry).await {
+                    tracing::error!("failed to append batch entry to write-ahead log: {e}");
+                }
+            }
+        }
+        results
+    }
+}
+
+/// Picks the store backend from the environment: `SCHEMA_STORE_BACKEND=file`
+/// (using `SCHEMA_STORE_PATH`, default `schema-registry.wal.jsonl`) for the
+/// write-ahead-log-backed store, anything else (including unset) for the
+/// original in-memory store.
+pub async fn build_store() -> std::io::Result<SharedState> {
+    match std::env::var("SCHEMA_STORE_BACKEND").as_deref() {
+        Ok("file") => {
+            let path =
+                std::env::var("SCHEMA_STORE_PATH").unwrap_or_else(|_| "schema-registry.wal.jsonl".to_string());
+            Ok(Arc::new(FileBackedStore::open(path).await?))
+        }
+        _ => Ok(Arc::new(InMemoryStore::seeded())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    static TEST_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+    /// A path under the system temp dir unique to this test process/run, so
+    /// parallel `cargo test` runs don't collide on the same WAL file.
+    fn temp_wal_path() -> PathBuf {
+        let n = TEST_COUNTER.fetch_add(1, Ordering::Relaxed);
+        std::env::temp_dir().join(format!(
+            "schema-rustgistry-test-{}-{n}.wal.jsonl",
+            std::process::id()
+        ))
+    }
+
+    #[tokio::test]
+    async fn reopening_a_file_backed_store_reconstructs_identical_state() {
+        let path = temp_wal_path();
+        let _ = tokio::fs::remove_file(&path).await;
+
+        {
+            let store = FileBackedStore::open(&path).await.unwrap();
+            store
+                .register_subject_version("s1", InputSchema::new("\"long\"".to_string()))
+                .await
+                .unwrap();
+            store
+                .register_subject_version("s2", InputSchema::new("\"string\"".to_string()))
+                .await
+                .unwrap();
+            store.delete_subject_version("s1", 1, false).await.unwrap();
+            store.set_compatibility("s2", CompatibilityLevel::Full).await;
+        }
+
+        let reopened = FileBackedStore::open(&path).await.unwrap();
+
+        assert!(reopened
+            .get_subject_by_name_and_version("s1", VersionParam::Version(1))
+            .await
+            .is_none());
+        let s2 = reopened
+            .get_subject_by_name_and_version("s2", VersionParam::Version(1))
+            .await
+            .expect("s2 should have survived the restart");
+        assert_eq!(s2.schema, "\"string\"");
+        assert_eq!(
+            reopened.get_compatibility("s2").await,
+            CompatibilityLevel::Full
+        );
+
+        let _ = tokio::fs::remove_file(&path).await;
+    }
+
+    #[tokio::test]
+    async fn concurrent_registrations_replay_in_the_order_they_actually_happened() {
+        let path = temp_wal_path();
+        let _ = tokio::fs::remove_file(&path).await;
+
+        let store = FileBackedStore::open(&path).await.unwrap();
+        let (a_id, b_id) = tokio::join!(
+            store.register_subject_version("a", InputSchema::new("\"long\"".to_string())),
+            store.register_subject_version("b", InputSchema::new("\"string\"".to_string())),
+        );
+        let a_id = a_id.unwrap();
+        let b_id = b_id.unwrap();
+        drop(store);
+
+        let reopened = FileBackedStore::open(&path).await.unwrap();
+        assert_eq!(
+            reopened.get_subject_by_id(a_id).await.unwrap().name,
+            "a",
+            "replay must reconstruct the same id assignments as the live run"
+        );
+        assert_eq!(
+            reopened.get_subject_by_id(b_id).await.unwrap().name,
+            "b",
+            "replay must reconstruct the same id assignments as the live run"
+        );
+
+        let _ = tokio::fs::remove_file(&path).await;
+    }
+}
@@ -0,0 +1,142 @@
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::State;
+use axum::response::IntoResponse;
+use futures::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::domain::InputSchema;
+use crate::store::SharedState;
+use crate::VersionParam;
+
+/// One request multiplexed over the `/ws` connection. Each carries the
+/// client-chosen `request_id` so the reply can be correlated back to it,
+/// except `Subscribe`, which only configures which broadcast topics this
+/// connection wants to receive.
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientMessage {
+    Register {
+        request_id: String,
+        subject: String,
+        schema: String,
+    },
+    Lookup {
+        request_id: String,
+        subject: String,
+        version: VersionParam,
+    },
+    Subscribe {
+        topics: Vec<String>,
+    },
+}
+
+/// A reply to a correlated request. Broadcast events are pushed as their own
+/// `RegistryEvent` JSON, with no envelope and no `request_id`.
+#[derive(Serialize, Debug)]
+struct ServerReply {
+    topic: &'static str,
+    request_id: String,
+    message: Value,
+}
+
+const TOPIC_NEW_VERSION: &str = "new_version";
+
+pub async fn ws_handler(
+    ws: WebSocketUpgrade,
+    State(data): State<SharedState>,
+) -> impl IntoResponse {
+    ws.on_upgrade(|socket| handle_socket(socket, data))
+}
+
+async fn handle_socket(socket: WebSocket, data: SharedState) {
+    let (mut sender, mut receiver) = socket.split();
+    let mut events = data.subscribe_events().await;
+    let mut subscribed_topics: Vec<String> = Vec::new();
+
+    loop {
+        tokio::select! {
+            incoming = receiver.next() => {
+                let Some(Ok(message)) = incoming else {
+                    break;
+                };
+                let Message::Text(text) = message else {
+                    continue;
+                };
+                let Ok(client_message) = serde_json::from_str::<ClientMessage>(&text) else {
+                    continue;
+                };
+                let Some(reply) = handle_client_message(&data, client_message, &mut subscribed_topics).await else {
+                    continue;
+                };
+                if sender.send(Message::Text(reply)).await.is_err() {
+                    break;
+                }
+            }
+            event = events.recv() => {
+                let Ok(event) = event else {
+                    continue;
+                };
+                if !subscribed_topics.iter().any(|t| t == TOPIC_NEW_VERSION) {
+                    continue;
+                }
+                let Ok(payload) = serde_json::to_string(&event) else {
+                    continue;
+                };
+                if sender.send(Message::Text(payload)).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+async fn handle_client_message(
+    data: &SharedState,
+    message: ClientMessage,
+    subscribed_topics: &mut Vec<String>,
+) -> Option<String> {
+    match message {
+        ClientMessage::Register {
+            request_id,
+            subject,
+            schema,
+        } => {
+            let result = data
+                .register_subject_version(&subject, InputSchema::new(schema))
+                .await;
+            let message = match result {
+                Ok(id) => json!({ "id": id }),
+                Err(e) => json!({ "error": e }),
+            };
+            reply("register", request_id, message)
+        }
+        ClientMessage::Lookup {
+            request_id,
+            subject,
+            version,
+        } => {
+            let message = match data
+                .get_subject_by_name_and_version(&subject, version)
+                .await
+            {
+                Some(subject) => serde_json::to_value(subject).unwrap_or(Value::Null),
+                None => json!({ "error": "not found" }),
+            };
+            reply("lookup", request_id, message)
+        }
+        ClientMessage::Subscribe { topics } => {
+            subscribed_topics.extend(topics);
+            None
+        }
+    }
+}
+
+fn reply(topic: &'static str, request_id: String, message: Value) -> Option<String> {
+    serde_json::to_string(&ServerReply {
+        topic,
+        request_id,
+        message,
+    })
+    .ok()
+}